@@ -0,0 +1,14 @@
+use tracing::Level;
+use tracing::metadata::LevelFilter;
+
+fn main() {
+    tracing_subscriber::fmt()
+        .with_writer(tracing_build_script::BuildScriptMakeWriter::builder().with_threshold(Level::ERROR).build())
+        .with_ansi(false)
+        .without_time()
+        .with_max_level(LevelFilter::TRACE)
+        .init();
+
+    tracing::warn!("below the custom threshold, stays informational");
+    tracing::error!("at the custom threshold, becomes a cargo warning");
+}
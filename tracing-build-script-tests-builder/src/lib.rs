@@ -0,0 +1,14 @@
+#![cfg(test)]
+
+use std::path::Path;
+
+#[test]
+fn test_custom_threshold_only_routes_the_configured_level_to_warning() {
+    let out_dir = Path::new(env!("OUT_DIR"));
+
+    let warn_output = std::fs::read_to_string(out_dir.join("../output")).unwrap();
+    assert_eq!(warn_output, "cargo::warning=ERROR build_script_build: at the custom threshold, becomes a cargo warning\n");
+
+    let info_output = std::fs::read_to_string(out_dir.join("../stderr")).unwrap();
+    assert_eq!(info_output, " WARN build_script_build: below the custom threshold, stays informational\n");
+}
@@ -0,0 +1,13 @@
+fn main() {
+    tracing_subscriber::fmt()
+        .with_writer(tracing_build_script::BuildScriptMakeWriter::builder().with_cargo_error_for_errors(true).build())
+        .with_ansi(false)
+        .without_time()
+        .init();
+
+    // `cargo::error=` causes cargo to fail this crate's build once this script exits, so unlike
+    // its sibling fixtures this crate's own `cargo test` never gets to run. The assertion lives in
+    // `tracing-build-script-tests`'s `test_cargo_error_for_errors_aborts_build`, which builds this
+    // crate as a subprocess and inspects cargo's own failure output instead.
+    tracing::error!("feature x is unavailable on this target");
+}
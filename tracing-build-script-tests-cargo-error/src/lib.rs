@@ -0,0 +1,6 @@
+//! Deliberately-failing fixture for the `cargo::error=` channel.
+//!
+//! This crate's `build.rs` emits a `cargo::error=` directive, which cargo treats as a build
+//! failure regardless of the build script's exit code — so this crate is never expected to finish
+//! building, and has no tests of its own. See `tracing-build-script-tests`'s
+//! `test_cargo_error_for_errors_aborts_build` for the assertion against this behaviour.
@@ -0,0 +1,19 @@
+#![cfg(test)]
+
+use std::path::Path;
+
+/// Each directive gets its own `CargoDirectiveWriter`, so this also exercises that the held-back
+/// newline terminator is re-emitted on drop: without it the three lines below would run together
+/// on one line that cargo can't parse.
+#[test]
+fn test_directives_round_trip_one_per_line() {
+    let out_dir = Path::new(env!("OUT_DIR"));
+    let output = std::fs::read_to_string(out_dir.join("../output")).unwrap();
+
+    assert_eq!(
+        output,
+        "cargo::rustc-cfg=has_feature_x\n\
+        cargo::rustc-env=BUILD_WIDGET_VERSION=1.2.3\n\
+        cargo::rerun-if-changed=widget.proto\n"
+    );
+}
@@ -0,0 +1,17 @@
+use tracing::metadata::LevelFilter;
+use tracing_build_script::DirectiveSyntax;
+
+fn main() {
+    tracing_subscriber::fmt()
+        .with_writer(tracing_build_script::CargoDirectiveMakeWriter::new(DirectiveSyntax::Legacy))
+        .with_ansi(false)
+        .with_level(false)
+        .with_target(false)
+        .without_time()
+        .with_max_level(LevelFilter::TRACE)
+        .init();
+
+    tracing_build_script::cargo_rustc_cfg!("has_feature_x");
+    tracing_build_script::cargo_rustc_env!("BUILD_WIDGET_VERSION", "1.2.3");
+    tracing_build_script::cargo_rerun_if_changed!("widget.proto");
+}
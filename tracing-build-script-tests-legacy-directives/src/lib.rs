@@ -0,0 +1,16 @@
+#![cfg(test)]
+
+use std::path::Path;
+
+#[test]
+fn test_legacy_syntax_uses_single_colon_prefix() {
+    let out_dir = Path::new(env!("OUT_DIR"));
+    let output = std::fs::read_to_string(out_dir.join("../output")).unwrap();
+
+    assert_eq!(
+        output,
+        "cargo:rustc-cfg=has_feature_x\n\
+        cargo:rustc-env=BUILD_WIDGET_VERSION=1.2.3\n\
+        cargo:rerun-if-changed=widget.proto\n"
+    );
+}
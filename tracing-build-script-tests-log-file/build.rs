@@ -0,0 +1,13 @@
+use tracing::metadata::LevelFilter;
+
+fn main() {
+    tracing_subscriber::fmt()
+        .with_writer(tracing_build_script::BuildScriptMakeWriter::builder().with_log_file(true).build())
+        .with_ansi(false)
+        .without_time()
+        .with_max_level(LevelFilter::TRACE)
+        .init();
+
+    tracing::info!("building widget");
+    tracing::warn!("widget config missing, using default");
+}
@@ -0,0 +1,15 @@
+#![cfg(test)]
+
+use std::path::Path;
+
+#[test]
+fn test_log_file_tees_every_event_regardless_of_channel() {
+    let out_dir = Path::new(env!("OUT_DIR"));
+    let log = std::fs::read_to_string(out_dir.join("tracing-build-script.log")).unwrap();
+
+    assert_eq!(
+        log,
+        " INFO build_script_build: building widget\n \
+        WARN build_script_build: widget config missing, using default\n"
+    );
+}
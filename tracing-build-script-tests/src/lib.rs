@@ -53,3 +53,18 @@ fn test_informational_output() {
         INFO build_script_build: other\rspecial\tchar\0a\tb\"c\\\n"
     );
 }
+
+/// `cargo::error=` aborts the build regardless of the build script's exit code, so the crate that
+/// emits it (`tracing-build-script-tests-cargo-error`) never finishes building and can't assert
+/// against its own captured output. Build it as a subprocess instead and inspect cargo's own
+/// failure report.
+#[test]
+fn test_cargo_error_for_errors_aborts_build() {
+    let fixture = Path::new(env!("CARGO_MANIFEST_DIR")).join("../tracing-build-script-tests-cargo-error");
+
+    let output = std::process::Command::new(env!("CARGO")).args(["build", "--quiet"]).current_dir(&fixture).output().unwrap();
+
+    assert!(!output.status.success(), "cargo::error= should have failed the build");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("feature x is unavailable on this target"), "expected the directive's message in cargo's failure output, got:\n{stderr}");
+}
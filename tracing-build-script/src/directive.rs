@@ -0,0 +1,183 @@
+use crate::DirectiveSyntax;
+use std::io::{self, Write};
+use tracing::Metadata;
+use tracing_subscriber::fmt::MakeWriter;
+
+/// The state of a single directive's trailing newline.
+///
+/// Unlike [`BuildScriptWriter`](crate::BuildScriptWriter), a directive cannot contain a newline at
+/// all, so there is nothing to escape: a trailing newline (the terminator [`tracing`] appends to
+/// every event) is held back until [`CargoDirectiveWriter`]'s [`Drop`] impl confirms it really was
+/// the terminator and re-emits it, while any other newline means the event spanned more than one
+/// line and is rejected.
+enum DirectiveState {
+    /// No output has been written yet, the directive's prefix needs to be written next.
+    Init,
+    /// The prefix was written and the last byte seen so far was not a newline/carriage return.
+    Normal,
+    /// The prefix was written and the last byte seen so far was a newline/carriage return that was
+    /// held back, since it might just be the event's terminator.
+    PendingTerminator,
+}
+
+fn ends_in_newline(buf: &[u8]) -> bool {
+    matches!(buf.last(), Some(b'\n' | b'\r'))
+}
+
+struct CargoDirectiveWriterInner {
+    state: DirectiveState,
+    prefix: Vec<u8>,
+    writer: io::Stdout,
+}
+
+/// [`Write`](std::io::Write) implementation returned by [`CargoDirectiveMakeWriter`].
+pub struct CargoDirectiveWriter(Option<CargoDirectiveWriterInner>);
+
+impl Drop for CargoDirectiveWriter {
+    fn drop(&mut self) {
+        // Each event gets a fresh writer, so a terminator held back by `write_all` (because it
+        // might just have been a coincidental trailing newline within the event) is in fact the
+        // event's terminator by the time this writer is dropped. Re-emit it so consecutive
+        // directives end up on their own line instead of running into each other.
+        if let Some(inner) = &mut self.0 {
+            if let DirectiveState::PendingTerminator = inner.state {
+                let _ = inner.writer.write_all(b"\n");
+            }
+        }
+    }
+}
+
+impl Write for CargoDirectiveWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.write_all(buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.0 {
+            Some(inner) => inner.writer.flush(),
+            None => Ok(()),
+        }
+    }
+
+    fn write_all(&mut self, mut buf: &[u8]) -> io::Result<()> {
+        let Some(inner) = &mut self.0 else {
+            // the event's target did not start with `cargo::`, this writer is a no-op for it
+            return Ok(());
+        };
+
+        if let DirectiveState::PendingTerminator = inner.state {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "cargo directives must be a single line"));
+        }
+
+        let terminated = ends_in_newline(buf);
+        if terminated {
+            buf = &buf[..buf.len() - 1];
+        }
+
+        // reject before writing anything, so a multi-line event leaves no partial directive behind
+        if buf.iter().any(|&ch| ch == b'\n' || ch == b'\r') {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "cargo directives must be a single line"));
+        }
+
+        if let DirectiveState::Init = inner.state {
+            inner.writer.write_all(&inner.prefix)?;
+        }
+        inner.state = if terminated { DirectiveState::PendingTerminator } else { DirectiveState::Normal };
+
+        inner.writer.write_all(buf)
+    }
+}
+
+/// [`MakeWriter`](tracing_subscriber::fmt::MakeWriter) that turns events whose `target` begins with
+/// `cargo::` into the corresponding single-line
+/// [cargo build-script directive](https://doc.rust-lang.org/cargo/reference/build-scripts.html#outputs-of-the-build-script):
+/// an event `tracing::info!(target: "cargo::rustc-cfg", "has_feature_x")` produces the line
+/// `cargo::rustc-cfg=has_feature_x`.
+///
+/// Events whose target does not start with `cargo::` are discarded. Compose this with
+/// [`BuildScriptMakeWriter`](crate::BuildScriptMakeWriter) via
+/// [`MakeWriterExt::and`](tracing_subscriber::fmt::writer::MakeWriterExt::and) if both directives and
+/// regular log output are needed.
+///
+/// # Formatting
+/// This writer only receives whatever the subscriber's event formatter writes, which by default
+/// includes the level and target (e.g. `cargo::rustc-cfg= INFO build_script_build: has_feature_x`,
+/// which cargo cannot parse). Disable both so only the message reaches the writer:
+///
+/// ```
+/// tracing_subscriber::fmt()
+///     .with_writer(tracing_build_script::CargoDirectiveMakeWriter::default())
+///     .with_level(false)
+///     .with_target(false)
+///     .without_time()
+///     .init();
+/// ```
+///
+/// Directives are strictly single-line: a message containing an embedded `\n` or `\r` causes the
+/// write to fail with an [`io::Error`] instead of being escaped. [`tracing`]'s [`MakeWriter`]
+/// contract has no way to surface that error to the caller, so such an event is silently dropped
+/// rather than reported — keep messages passed to [`cargo_rustc_cfg`], [`cargo_rustc_env`] and
+/// [`cargo_rerun_if_changed`] single-line.
+///
+/// See [`cargo_rustc_cfg`], [`cargo_rustc_env`] and [`cargo_rerun_if_changed`] for ergonomic helpers
+/// that emit directives through this writer.
+pub struct CargoDirectiveMakeWriter {
+    directive_syntax: DirectiveSyntax,
+}
+
+impl CargoDirectiveMakeWriter {
+    /// Create a writer that emits directives using the given [`DirectiveSyntax`].
+    pub fn new(directive_syntax: DirectiveSyntax) -> Self {
+        Self { directive_syntax }
+    }
+}
+
+impl Default for CargoDirectiveMakeWriter {
+    /// Defaults to [`DirectiveSyntax::Modern`].
+    fn default() -> Self {
+        Self::new(DirectiveSyntax::default())
+    }
+}
+
+impl<'a> MakeWriter<'a> for CargoDirectiveMakeWriter {
+    type Writer = CargoDirectiveWriter;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        CargoDirectiveWriter(None)
+    }
+
+    fn make_writer_for(&'a self, meta: &Metadata<'_>) -> Self::Writer {
+        let inner = meta.target().strip_prefix("cargo::").map(|directive| CargoDirectiveWriterInner {
+            state: DirectiveState::Init,
+            prefix: format!("{}{directive}=", self.directive_syntax.prefix()).into_bytes(),
+            writer: io::stdout(),
+        });
+
+        CargoDirectiveWriter(inner)
+    }
+}
+
+/// Emit a `cargo::rustc-cfg=<cfg>` directive through [`CargoDirectiveMakeWriter`].
+#[macro_export]
+macro_rules! cargo_rustc_cfg {
+    ($($arg:tt)*) => {
+        tracing::info!(target: "cargo::rustc-cfg", $($arg)*)
+    };
+}
+
+/// Emit a `cargo::rustc-env=<key>=<value>` directive through [`CargoDirectiveMakeWriter`].
+#[macro_export]
+macro_rules! cargo_rustc_env {
+    ($key:expr, $value:expr) => {
+        tracing::info!(target: "cargo::rustc-env", "{}={}", $key, $value)
+    };
+}
+
+/// Emit a `cargo::rerun-if-changed=<path>` directive through [`CargoDirectiveMakeWriter`].
+#[macro_export]
+macro_rules! cargo_rerun_if_changed {
+    ($path:expr) => {
+        tracing::info!(target: "cargo::rerun-if-changed", "{}", $path)
+    };
+}
@@ -1,7 +1,70 @@
-use std::{io, io::Write};
+use std::{
+    fs,
+    io::{self, Write},
+    path::Path,
+    sync::{Arc, Mutex},
+};
 use tracing::{Level, Metadata};
 use tracing_subscriber::fmt::MakeWriter;
 
+mod directive;
+pub use directive::{CargoDirectiveMakeWriter, CargoDirectiveWriter};
+
+/// Name of the log file written in `OUT_DIR` when [`BuildScriptMakeWriterBuilder::with_log_file`] is enabled.
+const LOG_FILE_NAME: &str = "tracing-build-script.log";
+
+/// Which [cargo build-script directive](https://doc.rust-lang.org/cargo/reference/build-scripts.html#outputs-of-the-build-script)
+/// syntax to emit.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DirectiveSyntax {
+    /// The `cargo::directive=value` syntax, understood since cargo 1.77.
+    Modern,
+    /// The legacy `cargo:directive=value` syntax, understood by every cargo version. Needed for
+    /// crates whose `rust-version`/MSRV predates cargo 1.77, since older cargo silently ignores
+    /// `cargo::` directives and drops the message.
+    Legacy,
+}
+
+impl DirectiveSyntax {
+    fn prefix(self) -> &'static str {
+        match self {
+            Self::Modern => "cargo::",
+            Self::Legacy => "cargo:",
+        }
+    }
+
+    /// Detect the syntax understood by the cargo running this build script, by parsing the version
+    /// reported by the `CARGO` build-script environment variable. Falls back to [`Self::Modern`] if
+    /// detection fails for any reason, e.g. `CARGO` is unset, the binary cannot be run, or its
+    /// version cannot be parsed.
+    pub fn detect() -> Self {
+        Self::detect_from_cargo_env().unwrap_or(Self::Modern)
+    }
+
+    fn detect_from_cargo_env() -> Option<Self> {
+        let cargo = std::env::var_os("CARGO")?;
+        let output = std::process::Command::new(cargo).arg("--version").output().ok()?;
+        if !output.status.success() {
+            return None;
+        }
+
+        let stdout = String::from_utf8(output.stdout).ok()?;
+        let version = stdout.split_whitespace().nth(1)?;
+        let mut version_parts = version.split('.');
+        let major: u32 = version_parts.next()?.parse().ok()?;
+        let minor: u32 = version_parts.next()?.parse().ok()?;
+
+        Some(if (major, minor) >= (1, 77) { Self::Modern } else { Self::Legacy })
+    }
+}
+
+impl Default for DirectiveSyntax {
+    /// Defaults to [`Self::Modern`], matching the previous hardcoded behaviour.
+    fn default() -> Self {
+        Self::Modern
+    }
+}
+
 enum ErrorAndWarnState {
     /// Initial state, no output has been written yet
     /// cargo::warning= needs to be written next
@@ -33,33 +96,154 @@ enum BuildScriptWriterInner {
     ErrorsAndWarnings {
         state: ErrorAndWarnState,
         writer: io::Stdout,
+        prefix: Vec<u8>,
+    },
+    Errors {
+        state: ErrorAndWarnState,
+        writer: io::Stdout,
+        prefix: Vec<u8>,
     },
 }
 
+/// Write `buf` to `writer`, prepending `prefix` and escaping embedded newlines the same way
+/// regardless of whether `prefix` is `cargo::warning=`/`cargo:warning=` or `cargo::error=`/`cargo:error=`.
+fn write_prefixed(state: &mut ErrorAndWarnState, writer: &mut io::Stdout, prefix: &[u8], mut buf: &[u8]) -> io::Result<()> {
+    // We will need to issue multiple write calls to the writer (to avoid heap allocation)
+    // so we need to lock it to prevent other threads from clobbering our output.
+    let mut writer = writer.lock();
+
+    // depending on the current state we may need to prefix the output
+    match *state {
+        ErrorAndWarnState::Init => {
+            writer.write_all(prefix)?;
+        },
+        ErrorAndWarnState::LastCharWasSpecial(ch) => {
+            writer.write_all(escape_special(ch))?;
+        },
+        ErrorAndWarnState::Normal => {},
+    }
+
+    // If the last char is a newline we need to remember that but cannot immediately
+    // write it out. This is because we cannot know yet if its needs to be escaped, there are two cases:
+    //
+    // 1. this call to write is not actually the last call to write that will happen it just happens to end with a newline
+    //    => we need to escape the newline
+    //
+    // 2. this call to write is actually the last call to write that will happen, and it ends with a newline
+    //    => we need to keep the newline as is, because it is the newline terminator of the log message
+    //       (tracing automatically appends a newline at the end of each message, like println!)
+    //
+    // Since we cannot decide which of these cases we are in at the moment, we need to delay writing the last character (if it is a newline) until we know that.
+    // We know which case we are in
+    //  either when we enter this function the next time (case 1)
+    //  or the next time or when we enter the destructor (case 2).
+    match buf.last().copied() {
+        Some(ch) if char_is_special(ch) => {
+            buf = &buf[..buf.len() - 1];
+            *state = ErrorAndWarnState::LastCharWasSpecial(ch);
+        },
+        _ => {
+            *state = ErrorAndWarnState::Normal;
+        },
+    }
+
+    let mut last_special_char = match buf.iter().position(|ch| char_is_special(*ch)) {
+        Some(pos) => {
+            writer.write_all(&buf[..pos])?;
+
+            let ret = buf[pos];
+            buf = &buf[pos + 1..];
+            ret
+        },
+        None => {
+            // fast path for messages without any special chars
+            writer.write_all(buf)?;
+            return Ok(());
+        },
+    };
+
+    loop {
+        writer.write_all(escape_special(last_special_char))?;
+
+        match buf.iter().position(|ch| char_is_special(*ch)) {
+            Some(pos) => {
+                writer.write_all(&buf[..pos])?;
+
+                last_special_char = buf[pos];
+                buf = &buf[pos + 1..];
+            },
+            None => {
+                writer.write_all(buf)?;
+                break;
+            },
+        }
+    }
+
+    Ok(())
+}
+
 /// A writer intended to support the [output capturing of build scripts](https://doc.rust-lang.org/cargo/reference/build-scripts.html#outputs-of-the-build-script).
 /// `BuildScriptWriter` can be used by [`tracing_subscriber::fmt::Subscriber`](tracing_subscriber::fmt::Subscriber) or [`tracing_subscriber::fmt::Layer`](tracing_subscriber::fmt::Layer)
 /// to enable capturing output in build scripts.
-pub struct BuildScriptWriter(BuildScriptWriterInner);
+pub struct BuildScriptWriter {
+    inner: BuildScriptWriterInner,
+    /// When set, every write is additionally teed to this file, unescaped, so that a full trace of
+    /// the build script's events survives even a non-verbose `cargo build`.
+    log_file: Option<Arc<Mutex<fs::File>>>,
+}
 
 impl BuildScriptWriter {
     /// Create a writer for informational events.
     /// Events will be written to stderr.
     pub fn informational() -> Self {
-        Self(BuildScriptWriterInner::Informational(io::stderr()))
+        Self { inner: BuildScriptWriterInner::Informational(io::stderr()), log_file: None }
     }
 
     /// Create a writer for warning and error events.
-    /// Events will be written to stdout after having `cargo::warning=` prepended.
-    pub fn errors_and_warnings() -> Self {
-        Self(BuildScriptWriterInner::ErrorsAndWarnings { state: ErrorAndWarnState::Init, writer: io::stdout() })
+    /// Events will be written to stdout after having `warning=` prepended with the given
+    /// [`DirectiveSyntax`]'s prefix (`cargo::warning=` or the legacy `cargo:warning=`).
+    pub fn errors_and_warnings(syntax: DirectiveSyntax) -> Self {
+        Self {
+            inner: BuildScriptWriterInner::ErrorsAndWarnings {
+                state: ErrorAndWarnState::Init,
+                writer: io::stdout(),
+                prefix: format!("{}warning=", syntax.prefix()).into_bytes(),
+            },
+            log_file: None,
+        }
+    }
+
+    /// Create a writer for error events that should fail the build.
+    /// Events will be written to stdout after having `error=` prepended with the given
+    /// [`DirectiveSyntax`]'s prefix (`cargo::error=` or the legacy `cargo:error=`), which
+    /// causes cargo to abort the build once the build script exits.
+    pub fn errors(syntax: DirectiveSyntax) -> Self {
+        Self {
+            inner: BuildScriptWriterInner::Errors {
+                state: ErrorAndWarnState::Init,
+                writer: io::stdout(),
+                prefix: format!("{}error=", syntax.prefix()).into_bytes(),
+            },
+            log_file: None,
+        }
+    }
+
+    /// Also tee every subsequent write, unescaped, to `log_file`.
+    fn tee_to(mut self, log_file: Arc<Mutex<fs::File>>) -> Self {
+        self.log_file = Some(log_file);
+        self
     }
 }
 
 impl Drop for BuildScriptWriter {
     fn drop(&mut self) {
-        if let BuildScriptWriterInner::ErrorsAndWarnings { state: ErrorAndWarnState::LastCharWasSpecial(ch), writer } =
-            &mut self.0
-        {
+        let pending = match &mut self.inner {
+            BuildScriptWriterInner::ErrorsAndWarnings { state, writer, prefix: _ }
+            | BuildScriptWriterInner::Errors { state, writer, prefix: _ } => Some((state, writer)),
+            BuildScriptWriterInner::Informational(_) => None,
+        };
+
+        if let Some((ErrorAndWarnState::LastCharWasSpecial(ch), writer)) = pending {
             let _ = writer.write(&[*ch]);
         }
     }
@@ -72,122 +256,228 @@ impl Write for BuildScriptWriter {
     }
 
     fn flush(&mut self) -> io::Result<()> {
-        match &mut self.0 {
+        let result = match &mut self.inner {
             BuildScriptWriterInner::Informational(writer) => writer.flush(),
-            BuildScriptWriterInner::ErrorsAndWarnings { writer, state: _ } => writer.flush(),
+            BuildScriptWriterInner::ErrorsAndWarnings { writer, state: _, prefix: _ }
+            | BuildScriptWriterInner::Errors { writer, state: _, prefix: _ } => writer.flush(),
+        };
+
+        if let Some(log_file) = &self.log_file {
+            log_file.lock().unwrap().flush()?;
         }
+
+        result
     }
 
-    fn write_all(&mut self, mut buf: &[u8]) -> io::Result<()> {
-        match &mut self.0 {
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        match &mut self.inner {
             BuildScriptWriterInner::Informational(writer) => writer.write_all(buf),
-            BuildScriptWriterInner::ErrorsAndWarnings { state, writer } => {
-                // We will need to issue multiple write calls to the writer (to avoid heap allocation)
-                // so we need to lock it to prevent other threads from clobbering our output.
-                let mut writer = writer.lock();
-
-                // depending on the current state we may need to prefix the output
-                match *state {
-                    ErrorAndWarnState::Init => {
-                        writer.write_all(b"cargo::warning=")?;
-                    },
-                    ErrorAndWarnState::LastCharWasSpecial(ch) => {
-                        writer.write_all(escape_special(ch))?;
-                    },
-                    ErrorAndWarnState::Normal => {},
-                }
+            BuildScriptWriterInner::ErrorsAndWarnings { state, writer, prefix } => write_prefixed(state, writer, prefix, buf),
+            BuildScriptWriterInner::Errors { state, writer, prefix } => write_prefixed(state, writer, prefix, buf),
+        }?;
 
-                // If the last char is a newline we need to remember that but cannot immediately
-                // write it out. This is because we cannot know yet if its needs to be escaped, there are two cases:
-                //
-                // 1. this call to write is not actually the last call to write that will happen it just happens to end with a newline
-                //    => we need to escape the newline
-                //
-                // 2. this call to write is actually the last call to write that will happen, and it ends with a newline
-                //    => we need to keep the newline as is, because it is the newline terminator of the log message
-                //       (tracing automatically appends a newline at the end of each message, like println!)
-                //
-                // Since we cannot decide which of these cases we are in at the moment, we need to delay writing the last character (if it is a newline) until we know that.
-                // We know which case we are in
-                //  either when we enter this function the next time (case 1)
-                //  or the next time or when we enter the destructor (case 2).
-                match buf.last().copied() {
-                    Some(ch) if char_is_special(ch) => {
-                        buf = &buf[..buf.len() - 1];
-                        *state = ErrorAndWarnState::LastCharWasSpecial(ch);
-                    },
-                    _ => {
-                        *state = ErrorAndWarnState::Normal;
-                    },
-                }
+        if let Some(log_file) = &self.log_file {
+            log_file.lock().unwrap().write_all(buf)?;
+        }
 
-                let mut last_special_char = match buf.iter().position(|ch| char_is_special(*ch)) {
-                    Some(pos) => {
-                        writer.write_all(&buf[..pos])?;
-
-                        let ret = buf[pos];
-                        buf = &buf[pos + 1..];
-                        ret
-                    },
-                    None => {
-                        // fast path for messages without any special chars
-                        writer.write_all(buf)?;
-                        return Ok(());
-                    },
-                };
-
-                loop {
-                    writer.write_all(escape_special(last_special_char))?;
-
-                    match buf.iter().position(|ch| char_is_special(*ch)) {
-                        Some(pos) => {
-                            writer.write_all(&buf[..pos])?;
-
-                            last_special_char = buf[pos];
-                            buf = &buf[pos + 1..];
-                        },
-                        None => {
-                            writer.write_all(buf)?;
-                            break;
-                        },
-                    }
-                }
+        Ok(())
+    }
+}
+
+/// The channel a [`BuildScriptMakeWriter`] routes an event to.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Channel {
+    /// The event is sent to stderr, where it is only visible when running with verbose build
+    /// output (`cargo build -vv`).
+    Informational,
+    /// The event is sent to stdout with [`cargo::warning=`](https://doc.rust-lang.org/cargo/reference/build-scripts.html#cargo-warning) prepended.
+    Warning,
+    /// The event is sent to stdout with [`cargo::error=`](https://doc.rust-lang.org/cargo/reference/build-scripts.html#cargo-error) prepended,
+    /// which causes cargo to fail the build once the build script exits.
+    Error,
+}
+
+/// Boxed predicate used by [`Router::Predicate`] and [`BuildScriptMakeWriterBuilder::with_router`].
+type RouterPredicate = Box<dyn Fn(&Metadata<'_>) -> Channel + Send + Sync>;
+
+/// How a [`BuildScriptMakeWriter`] decides which [`Channel`] an event is routed to.
+enum Router {
+    /// Events at or above `warning_threshold` are routed to [`Channel::Warning`], except that
+    /// `Level::ERROR` events go to [`Channel::Error`] when `route_errors_to_cargo_error` is set;
+    /// everything else goes to [`Channel::Informational`].
+    Threshold { warning_threshold: Level, route_errors_to_cargo_error: bool },
+    /// A user-supplied predicate decides the [`Channel`] for every event.
+    Predicate(RouterPredicate),
+}
 
-                Ok(())
+impl Router {
+    fn channel_for(&self, meta: &Metadata<'_>) -> Channel {
+        match self {
+            Self::Threshold { warning_threshold, route_errors_to_cargo_error } => {
+                if *route_errors_to_cargo_error && meta.level() == &Level::ERROR {
+                    Channel::Error
+                } else if meta.level() <= warning_threshold {
+                    Channel::Warning
+                } else {
+                    Channel::Informational
+                }
             },
+            Self::Predicate(predicate) => predicate(meta),
         }
     }
 }
 
+/// Builder for [`BuildScriptMakeWriter`], returned by [`BuildScriptMakeWriter::builder`].
+pub struct BuildScriptMakeWriterBuilder {
+    warning_threshold: Level,
+    route_errors_to_cargo_error: bool,
+    predicate: Option<RouterPredicate>,
+    log_file: bool,
+    directive_syntax: DirectiveSyntax,
+}
+
+impl BuildScriptMakeWriterBuilder {
+    /// Set the [`Level`] at and above which events are routed to [`Channel::Warning`] (i.e. printed
+    /// as `cargo::warning=`). Defaults to [`Level::WARN`], matching the previous hardcoded behaviour.
+    ///
+    /// Has no effect if [`with_router`](Self::with_router) is also set.
+    pub fn with_threshold(mut self, warning_threshold: Level) -> Self {
+        self.warning_threshold = warning_threshold;
+        self
+    }
+
+    /// Route `Level::ERROR` events to [`Channel::Error`] (`cargo::error=`) instead of
+    /// [`Channel::Warning`] (`cargo::warning=`). `cargo::error=` causes cargo to fail the build once
+    /// the build script exits, so this is opt-in and defaults to `false`.
+    ///
+    /// Has no effect if [`with_router`](Self::with_router) is also set.
+    pub fn with_cargo_error_for_errors(mut self, route_errors_to_cargo_error: bool) -> Self {
+        self.route_errors_to_cargo_error = route_errors_to_cargo_error;
+        self
+    }
+
+    /// Fully customize the routing of events to a [`Channel`], overriding
+    /// [`with_threshold`](Self::with_threshold) and [`with_cargo_error_for_errors`](Self::with_cargo_error_for_errors).
+    pub fn with_router(mut self, router: impl Fn(&Metadata<'_>) -> Channel + Send + Sync + 'static) -> Self {
+        self.predicate = Some(Box::new(router));
+        self
+    }
+
+    /// In addition to the usual stdout/stderr routing, tee every event (all levels, unescaped, with
+    /// its level prefix) to `$OUT_DIR/tracing-build-script.log`. This makes the full trace available
+    /// after a normal `cargo build`, without needing to re-run with `-vv`. Each build script
+    /// invocation truncates the file, so it always reflects only the most recent run.
+    ///
+    /// Disabled by default. [`build`](Self::build) panics if `OUT_DIR` is not set or the
+    /// log file cannot be opened, since both indicate the writer is not actually running in a build
+    /// script.
+    pub fn with_log_file(mut self, enabled: bool) -> Self {
+        self.log_file = enabled;
+        self
+    }
+
+    /// Set which [`DirectiveSyntax`] is used for the `cargo::warning=`/`cargo::error=` (or legacy
+    /// `cargo:warning=`/`cargo:error=`) directives this writer emits. Defaults to
+    /// [`DirectiveSyntax::Modern`]; use [`DirectiveSyntax::detect`] to pick automatically based on
+    /// the cargo running the build script.
+    pub fn with_directive_syntax(mut self, directive_syntax: DirectiveSyntax) -> Self {
+        self.directive_syntax = directive_syntax;
+        self
+    }
+
+    /// Build the configured [`BuildScriptMakeWriter`].
+    pub fn build(self) -> BuildScriptMakeWriter {
+        let router = match self.predicate {
+            Some(predicate) => Router::Predicate(predicate),
+            None => {
+                Router::Threshold { warning_threshold: self.warning_threshold, route_errors_to_cargo_error: self.route_errors_to_cargo_error }
+            },
+        };
+        let log_file = self.log_file.then(open_log_file);
+
+        BuildScriptMakeWriter { router, log_file, directive_syntax: self.directive_syntax }
+    }
+}
+
+/// Open (creating if necessary, truncating any previous run's contents) the log file in
+/// `OUT_DIR`, shared across every writer this [`BuildScriptMakeWriter`] constructs so all events
+/// end up in the same file.
+fn open_log_file() -> Arc<Mutex<fs::File>> {
+    let out_dir = std::env::var_os("OUT_DIR").expect("OUT_DIR is only set when running inside a build script");
+    let path = Path::new(&out_dir).join(LOG_FILE_NAME);
+    let file = fs::OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(true)
+        .open(&path)
+        .unwrap_or_else(|err| panic!("failed to open {}: {err}", path.display()));
+
+    Arc::new(Mutex::new(file))
+}
+
 /// [`MakeWriter`](tracing_subscriber::fmt::MakeWriter) implementation for [`BuildScriptWriter`](BuildScriptWriter)
 ///
 /// # Behaviour
-/// Events for Levels Error and Warn are printed to stdout with [`cargo::warning=`](https://doc.rust-lang.org/cargo/reference/build-scripts.html#cargo-warning) prepended.
+/// By default, events for Levels Error and Warn are printed to stdout with [`cargo::warning=`](https://doc.rust-lang.org/cargo/reference/build-scripts.html#cargo-warning) prepended.
 /// All other levels are sent to stderr, where they are only visible when running with verbose build output (`cargo build -vv`).
+/// Use [`builder`](Self::builder) to customize this, e.g. to change the threshold or route events by a custom predicate.
 ///
-/// Note: this writer explicitly does **not** use the [`cargo::error=`](https://doc.rust-lang.org/cargo/reference/build-scripts.html#cargo-error) instruction
-/// because it aborts the build with an error, which is not always desired.
+/// Note: by default this writer does **not** use the [`cargo::error=`](https://doc.rust-lang.org/cargo/reference/build-scripts.html#cargo-error) instruction
+/// because it aborts the build with an error, which is not always desired. Use
+/// [`BuildScriptMakeWriterBuilder::with_cargo_error_for_errors`] to opt in.
 ///
 /// # Example
 /// ```
 /// tracing_subscriber::fmt()
-///     .with_writer(tracing_build_script::BuildScriptMakeWriter)
+///     .with_writer(tracing_build_script::BuildScriptMakeWriter::default())
 ///     .init();
 /// ```
-pub struct BuildScriptMakeWriter;
+pub struct BuildScriptMakeWriter {
+    router: Router,
+    log_file: Option<Arc<Mutex<fs::File>>>,
+    directive_syntax: DirectiveSyntax,
+}
+
+impl BuildScriptMakeWriter {
+    /// Start building a [`BuildScriptMakeWriter`] with custom routing.
+    pub fn builder() -> BuildScriptMakeWriterBuilder {
+        BuildScriptMakeWriterBuilder {
+            warning_threshold: Level::WARN,
+            route_errors_to_cargo_error: false,
+            predicate: None,
+            log_file: false,
+            directive_syntax: DirectiveSyntax::default(),
+        }
+    }
+
+    fn tee(&self, writer: BuildScriptWriter) -> BuildScriptWriter {
+        match &self.log_file {
+            Some(log_file) => writer.tee_to(Arc::clone(log_file)),
+            None => writer,
+        }
+    }
+}
+
+impl Default for BuildScriptMakeWriter {
+    fn default() -> Self {
+        Self::builder().build()
+    }
+}
 
 impl<'a> MakeWriter<'a> for BuildScriptMakeWriter {
     type Writer = BuildScriptWriter;
 
     fn make_writer(&'a self) -> Self::Writer {
-        BuildScriptWriter::informational()
+        self.tee(BuildScriptWriter::informational())
     }
 
     fn make_writer_for(&'a self, meta: &Metadata) -> Self::Writer {
-        if meta.level() == &Level::ERROR || meta.level() == &Level::WARN {
-            BuildScriptWriter::errors_and_warnings()
-        } else {
-            BuildScriptWriter::informational()
-        }
+        let writer = match self.router.channel_for(meta) {
+            Channel::Warning => BuildScriptWriter::errors_and_warnings(self.directive_syntax),
+            Channel::Error => BuildScriptWriter::errors(self.directive_syntax),
+            Channel::Informational => BuildScriptWriter::informational(),
+        };
+
+        self.tee(writer)
     }
 }